@@ -0,0 +1,81 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration as StdDuration;
+use time::Duration;
+
+/// The Pomodoro schedule: how long focus/break periods last and how often a
+/// long break is taken instead of a short one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(with = "humantime_serde")]
+    pub focus: StdDuration,
+    #[serde(with = "humantime_serde")]
+    pub short_break: StdDuration,
+    #[serde(with = "humantime_serde")]
+    pub long_break: StdDuration,
+    /// how many focus cycles precede a long break
+    pub cycles_before_long_break: i32,
+    /// the waybar output template; see [`crate::Timer::render`] for the
+    /// supported `{placeholder}`s. Using `{seconds}` makes the scheduler
+    /// wake every second while a timer is running, instead of once a minute.
+    pub template: String,
+}
+
+/// the default template, reproducing the output this crate has always produced
+pub const DEFAULT_TEMPLATE: &str =
+    r#"{"text": "{minutes}", "alt": "{state}-{phase}", "tooltip": "{tooltip}", "class": "{css_class}"}"#;
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            focus: StdDuration::from_secs(25 * 60),
+            short_break: StdDuration::from_secs(5 * 60),
+            long_break: StdDuration::from_secs(25 * 60),
+            cycles_before_long_break: 4,
+            template: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from `$XDG_CONFIG_HOME/waybar-timer/config.toml` (falling
+    /// back to `~/.config/waybar-timer/config.toml`), or the default schedule if
+    /// no such file exists.
+    pub fn load() -> Self {
+        let path = Self::path();
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+                eprintln!("couldn't parse config at {}: {err}", path.display());
+                Config::default()
+            }),
+            Err(_) => Config::default(),
+        }
+    }
+
+    fn path() -> PathBuf {
+        if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+            PathBuf::from(dir).join("waybar-timer/config.toml")
+        } else {
+            let home = std::env::var("HOME").expect("HOME is not set");
+            PathBuf::from(home).join(".config/waybar-timer/config.toml")
+        }
+    }
+
+    /// the duration of the next timer for the given cycle count
+    pub fn next_duration(&self, cycles: i32) -> Duration {
+        let std_duration = if cycles % 2 == 0 {
+            self.focus
+        } else {
+            let break_number = (cycles + 1) / 2;
+            let is_long_break = self.cycles_before_long_break > 0
+                && break_number % self.cycles_before_long_break == 0;
+            if is_long_break {
+                self.long_break
+            } else {
+                self.short_break
+            }
+        };
+        Duration::try_from(std_duration).unwrap_or(Duration::ZERO)
+    }
+}
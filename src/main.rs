@@ -1,28 +1,133 @@
+mod config;
+mod persist;
+mod restart;
+
 use clap::Parser;
+use config::Config;
 use serde_dispatch::serde_dispatch;
+use std::collections::HashMap;
 use std::error::Error;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
 use std::os::linux::net::SocketAddrExt;
 use std::os::unix::net::{SocketAddr, UnixListener, UnixStream};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use time::{Duration, OffsetDateTime};
 
 /// The name of the "updates" socket in the abstract namespace.
 const SOCKET_NAME_UPDATES: &[u8] = b"waybar_timer_updates";
 /// The name of the "commands" socket in the abstract namespace.
 const SOCKET_NAME_COMMANDS: &[u8] = b"waybar_timer_commands";
-/// The interval in which updates are pulled.
-const INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// Upper bound on how long the scheduler sleeps when no timer is running, so
+/// it still notices a `SIGHUP` in bounded time even with nothing to wake it.
+const MAX_IDLE_WAIT: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// The timer name used when the caller doesn't name one explicitly.
+const DEFAULT_TIMER_NAME: &str = "default";
+/// The special timer name that selects the aggregate view of all timers.
+const AGGREGATE_TIMER_NAME: &str = "*";
+
+/// the daemon's state, shared with anything (like a notification action
+/// listener) that needs to call back into it from another thread
+type SharedState = Arc<(Mutex<ServerState>, Condvar)>;
+
+/// A stable notification id for timer `name`, so each timer owns its own
+/// on-screen notification instead of every timer fighting over a shared one.
+fn notification_id(name: &str) -> u32 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish() as u32
+}
 
-fn send_notification(summary: String) {
+fn send_notification(summary: String, name: &str) {
     let _ = notify_rust::Notification::new()
         .appname("Waybar Timer")
-        .id(12345)
+        .id(notification_id(name))
         .summary(&summary)
         .urgency(notify_rust::Urgency::Low)
         .show();
 }
 
+/// The `(action_id, label)` buttons shown on a notification for a timer
+/// that's currently running: pause it, extend it, or cancel it.
+const RUNNING_ACTIONS: &[(&str, &str)] = &[("pause", "Pause"), ("extend", "+5 min"), ("cancel", "Cancel")];
+/// The `(action_id, label)` buttons shown on a notification for a timer
+/// that's currently paused: resume it, or cancel it.
+const PAUSED_ACTIONS: &[(&str, &str)] = &[("resume", "Resume"), ("cancel", "Cancel")];
+
+/// Shows a notification with `actions` buttons that control timer `name` on
+/// `shared`, and waits for whichever one (if any) the user picks. Runs
+/// entirely on its own thread: showing a notification (and waiting on it)
+/// can block on a slow or absent notification daemon, and this is called
+/// while `shared`'s lock is held by the caller.
+///
+/// Timer `name` can only ever have one live notification at a time: this
+/// closes out whatever notification previously occupied `shared`'s
+/// `notifications` slot for `name`, so its waiting thread returns instead of
+/// leaking, and so there's never more than one set of action buttons (for a
+/// popup that may already be gone) listening for the same timer.
+fn send_actionable_notification(
+    summary: String,
+    name: String,
+    shared: SharedState,
+    actions: &'static [(&'static str, &'static str)],
+) {
+    std::thread::spawn(move || {
+        // the previous notification shares `notification_id(&name)` with this
+        // one, so it's closed before this one is shown: closing it afterwards
+        // would close the new popup instead
+        let previous = {
+            let (mutex, _condvar) = &*shared;
+            mutex.lock().unwrap().notifications.remove(&name)
+        };
+        if let Some(previous) = previous {
+            previous.close();
+        }
+
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .appname("Waybar Timer")
+            .id(notification_id(&name))
+            .summary(&summary)
+            .urgency(notify_rust::Urgency::Low);
+        for (id, label) in actions {
+            notification.action(id, label);
+        }
+
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(err) => {
+                eprintln!("couldn't show notification: {err}");
+                return;
+            }
+        };
+
+        {
+            let (mutex, _condvar) = &*shared;
+            mutex
+                .lock()
+                .unwrap()
+                .notifications
+                .insert(name.clone(), handle.clone());
+        }
+
+        handle.wait_for_action(|action| {
+            let (mutex, condvar) = &*shared;
+            let mut state = mutex.lock().unwrap();
+            let result = match action {
+                "pause" | "resume" => state.togglepause(name.clone()),
+                "extend" => state.increase(name.clone(), 5 * 60),
+                "cancel" => state.cancel(name.clone()),
+                _ => return,
+            };
+            if let Err(err) = result {
+                eprintln!("couldn't {action} timer \"{name}\": {err}");
+            }
+            drop(state);
+            condvar.notify_one();
+        });
+    });
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 enum WorldError {
     NoTimerExisting,
@@ -40,21 +145,23 @@ impl Error for WorldError {}
 
 #[serde_dispatch]
 trait World {
-    fn cancel(&mut self) -> Result<(), WorldError>;
-    fn start(&mut self, command: Option<String>) -> Result<(), WorldError>;
-    fn increase(&mut self, seconds: i64) -> Result<(), WorldError>;
-    fn togglepause(&mut self) -> Result<(), WorldError>;
-    fn skip(&mut self) -> Result<(), WorldError>;
+    fn cancel(&mut self, name: String) -> Result<(), WorldError>;
+    fn start(&mut self, name: String, command: Option<String>) -> Result<(), WorldError>;
+    fn increase(&mut self, name: String, seconds: i64) -> Result<(), WorldError>;
+    fn togglepause(&mut self, name: String) -> Result<(), WorldError>;
+    fn skip(&mut self, name: String) -> Result<(), WorldError>;
+    fn list(&mut self) -> Result<Vec<String>, WorldError>;
+    fn remove(&mut self, name: String) -> Result<(), WorldError>;
 }
 
 #[derive(Debug)]
-struct Timer {
-    cycles: i32,
-    kind: TimerKind,
+pub(crate) struct Timer {
+    pub(crate) cycles: i32,
+    pub(crate) kind: TimerKind,
 }
 
 #[derive(Debug)]
-enum TimerKind {
+pub(crate) enum TimerKind {
     Idle,
     Running {
         expiry: OffsetDateTime,
@@ -67,8 +174,15 @@ enum TimerKind {
 }
 
 impl Timer {
-    /// updates timer, potentially executes action, and returns formatted string for waybar
-    fn update(&mut self) -> String {
+    fn new() -> Self {
+        Timer {
+            cycles: 0,
+            kind: TimerKind::Idle,
+        }
+    }
+
+    /// advances the timer, executing its action and flipping it back to idle if it expired
+    fn tick(&mut self) {
         let now = OffsetDateTime::now_local().unwrap();
 
         // check if timer expired
@@ -88,74 +202,158 @@ impl Timer {
                 };
             }
         }
+    }
 
-        let focus_break = if self.cycles % 2 == 0 {
-            "focus"
-        } else {
-            "break"
-        };
+    /// renders `config.template` against the timer's current state; does not mutate it
+    ///
+    /// Supported placeholders: `{minutes}`, `{seconds}`, `{state}` (standby/running/paused),
+    /// `{cycle}`, `{expiry_time}`, `{phase}` (focus/break), `{tooltip}` and `{css_class}`.
+    fn render(&self, config: &Config) -> String {
+        let now = OffsetDateTime::now_local().unwrap();
+
+        let phase = if self.cycles % 2 == 0 { "focus" } else { "break" };
 
-        // print new output to stdout (for waybar)
-        let (text, alt, tooltip, css_class) = match self.kind {
-            TimerKind::Idle { .. } => (0, "standby", "No timer set".into(), "idle"),
+        let (minutes, seconds, state, expiry_time, tooltip, css_class) = match self.kind {
+            TimerKind::Idle { .. } => (0, 0, "standby", String::new(), "No timer set".into(), "idle"),
             TimerKind::Running { expiry, .. } => {
                 let time_left = expiry - now;
                 let minutes_left = time_left.whole_minutes() + 1;
-                let tooltip = Self::tooltip(&expiry);
-                (minutes_left, "running", tooltip, focus_break)
+                let seconds_left = time_left.whole_seconds().max(0);
+                (
+                    minutes_left,
+                    seconds_left,
+                    "running",
+                    Self::time(&expiry),
+                    Self::tooltip(&expiry),
+                    phase,
+                )
             }
             TimerKind::Paused { time_left, .. } => {
                 let minutes_left = time_left.whole_minutes() + 1;
-                let tooltip = "Timer paused".into();
-                (minutes_left, "paused", tooltip, focus_break)
+                let seconds_left = time_left.whole_seconds().max(0);
+                (
+                    minutes_left,
+                    seconds_left,
+                    "paused",
+                    String::new(),
+                    "Timer paused".into(),
+                    phase,
+                )
             }
         };
 
-        format!("{{\"text\": \"{text}\", \"alt\": \"{alt}-{focus_break}\", \"tooltip\": \"{tooltip}\", \"class\": \"{css_class}\"}}")
+        config
+            .template
+            .replace("{minutes}", &minutes.to_string())
+            .replace("{seconds}", &seconds.to_string())
+            .replace("{state}", state)
+            .replace("{cycle}", &self.cycles.to_string())
+            .replace("{expiry_time}", &expiry_time)
+            .replace("{phase}", phase)
+            .replace("{tooltip}", &tooltip)
+            .replace("{css_class}", css_class)
+    }
+
+    /// how long until this timer's rendering will next change on its own (a
+    /// `minutes_left`/`seconds_left` tick or an expiry), or `None` if nothing
+    /// will change until a command arrives
+    ///
+    /// Wakes once a minute, unless `config.template` uses `{seconds}`, in
+    /// which case it wakes every second so that placeholder doesn't go stale.
+    fn next_change(&self, config: &Config) -> Option<std::time::Duration> {
+        match self.kind {
+            TimerKind::Idle | TimerKind::Paused { .. } => None,
+            TimerKind::Running { expiry, .. } => {
+                let time_left = expiry - OffsetDateTime::now_local().unwrap();
+                if time_left <= Duration::ZERO {
+                    return Some(std::time::Duration::ZERO);
+                }
+                // `minutes_left`/`seconds_left` change the instant
+                // `time_left` crosses a multiple of the granularity, so wake
+                // from the fractional remainder rather than flooring to
+                // whole seconds first (which made exact-boundary remainders
+                // sleep a full granularity too long).
+                let granularity = if config.template.contains("{seconds}") {
+                    1.0
+                } else {
+                    60.0
+                };
+                let seconds_left = time_left.as_seconds_f64();
+                let seconds_until_wake = (seconds_left % granularity).min(seconds_left);
+                Some(std::time::Duration::from_secs_f64(seconds_until_wake))
+            }
+        }
+    }
+
+    /// short one-line summary used by the aggregate view
+    fn summary(&self) -> Option<String> {
+        match self.kind {
+            TimerKind::Idle => None,
+            TimerKind::Running { expiry, .. } => {
+                let minutes_left = (expiry - OffsetDateTime::now_local().unwrap()).whole_minutes() + 1;
+                Some(format!("{minutes_left}"))
+            }
+            TimerKind::Paused { time_left, .. } => {
+                let minutes_left = time_left.whole_minutes() + 1;
+                Some(format!("{minutes_left}\u{23f8}"))
+            }
+        }
     }
 
     fn tooltip(expiry: &OffsetDateTime) -> String {
+        format!("Timer expires at {}", Self::time(expiry))
+    }
+
+    fn time(instant: &OffsetDateTime) -> String {
         let format_desc = time::macros::format_description!("[hour]:[minute]");
-        let expiry_str = expiry.format(&format_desc).unwrap();
-        format!("Timer expires at {expiry_str}")
+        instant.format(&format_desc).unwrap()
     }
-}
 
-impl World for Timer {
-    fn cancel(&mut self) -> Result<(), WorldError> {
+    fn cancel(&mut self, name: &str) -> Result<(), WorldError> {
         match self.kind {
             TimerKind::Idle => {
                 self.cycles = 0;
             }
-            _ => send_notification("Timer canceled".into()),
+            _ => send_notification("Timer canceled".into(), name),
         };
         self.kind = TimerKind::Idle;
         Ok(())
     }
 
-    fn start(&mut self, command: Option<String>) -> Result<(), WorldError> {
-        let minutes = match self.cycles % 8 {
-            1 | 3 | 5 => 5,
-            _ => 25,
-        };
+    fn start(
+        &mut self,
+        name: &str,
+        shared: &SharedState,
+        config: &Config,
+        command: Option<String>,
+    ) -> Result<(), WorldError> {
         match self.kind {
             TimerKind::Idle => {
-                let expiry = OffsetDateTime::now_local().unwrap()
-                    + Duration::minutes(minutes.into())
-                    - Duration::MILLISECOND;
-                send_notification(Self::tooltip(&expiry));
+                let duration = config.next_duration(self.cycles);
+                let expiry = OffsetDateTime::now_local().unwrap() + duration - Duration::MILLISECOND;
+                send_actionable_notification(
+                    Self::tooltip(&expiry),
+                    name.to_string(),
+                    shared.clone(),
+                    RUNNING_ACTIONS,
+                );
                 self.kind = TimerKind::Running { expiry, command };
                 Ok(())
             }
-            TimerKind::Paused { .. } | TimerKind::Running { .. } => self.togglepause(),
+            TimerKind::Paused { .. } | TimerKind::Running { .. } => self.togglepause(name, shared),
         }
     }
 
-    fn increase(&mut self, seconds: i64) -> Result<(), WorldError> {
+    fn increase(&mut self, name: &str, shared: &SharedState, seconds: i64) -> Result<(), WorldError> {
         match self.kind {
             TimerKind::Running { ref mut expiry, .. } => {
                 *expiry += Duration::seconds(seconds);
-                send_notification(Self::tooltip(&expiry));
+                send_actionable_notification(
+                    Self::tooltip(&expiry),
+                    name.to_string(),
+                    shared.clone(),
+                    RUNNING_ACTIONS,
+                );
                 Ok(())
             }
             TimerKind::Paused {
@@ -169,7 +367,7 @@ impl World for Timer {
         }
     }
 
-    fn skip(&mut self) -> Result<(), WorldError> {
+    fn skip(&mut self, name: &str, shared: &SharedState) -> Result<(), WorldError> {
         match self.kind {
             TimerKind::Idle => Err(WorldError::NoTimerExisting),
             TimerKind::Running { ref mut expiry, .. } => {
@@ -180,19 +378,24 @@ impl World for Timer {
                 ref mut time_left, ..
             } => {
                 *time_left = Duration::ZERO;
-                self.togglepause()
+                self.togglepause(name, shared)
             }
         }
     }
 
-    fn togglepause(&mut self) -> Result<(), WorldError> {
+    fn togglepause(&mut self, name: &str, shared: &SharedState) -> Result<(), WorldError> {
         match self.kind {
             TimerKind::Running {
                 expiry,
                 ref mut command,
             } => {
                 let time_left = expiry - OffsetDateTime::now_local().unwrap();
-                send_notification("Timer paused".into());
+                send_actionable_notification(
+                    "Timer paused".into(),
+                    name.to_string(),
+                    shared.clone(),
+                    PAUSED_ACTIONS,
+                );
                 self.kind = TimerKind::Paused {
                     time_left,
                     command: command.take(),
@@ -204,7 +407,12 @@ impl World for Timer {
                 ref mut command,
             } => {
                 let expiry = OffsetDateTime::now_local().unwrap() + time_left;
-                send_notification(Self::tooltip(&expiry));
+                send_actionable_notification(
+                    Self::tooltip(&expiry),
+                    name.to_string(),
+                    shared.clone(),
+                    RUNNING_ACTIONS,
+                );
                 self.kind = TimerKind::Running {
                     expiry,
                     command: command.take(),
@@ -221,46 +429,151 @@ impl World for Timer {
 enum Args {
     /// Serve a timer API (should be called once at compositor startup)
     Serve,
-    /// Keep reading the latest status of the timer (should be called by waybar)
-    Hook,
+    /// Keep reading the latest status of a timer (should be called by waybar)
+    Hook {
+        /// Which named timer to render, or "*" to render an aggregate of all of them
+        #[arg(default_value = AGGREGATE_TIMER_NAME)]
+        name: String,
+    },
     /// Start a new timer
     New {
+        #[arg(default_value = DEFAULT_TIMER_NAME)]
+        name: String,
         command: Option<String>,
     },
-    /// Increase the current timer
+    /// Increase a timer
     Increase {
+        #[arg(default_value = DEFAULT_TIMER_NAME)]
+        name: String,
         seconds: u32,
     },
-    /// Decrease the current timer
+    /// Decrease a timer
     Decrease {
+        #[arg(default_value = DEFAULT_TIMER_NAME)]
+        name: String,
         seconds: u32,
     },
-    /// Pause or resume the current timer
-    Togglepause,
-    Skip,
-    /// Cancel the current timer
-    Cancel,
+    /// Pause or resume a timer
+    Togglepause {
+        #[arg(default_value = DEFAULT_TIMER_NAME)]
+        name: String,
+    },
+    Skip {
+        #[arg(default_value = DEFAULT_TIMER_NAME)]
+        name: String,
+    },
+    /// Cancel a timer
+    Cancel {
+        #[arg(default_value = DEFAULT_TIMER_NAME)]
+        name: String,
+    },
+    /// List all currently known timers
+    List,
+    /// Forget a timer entirely
+    Remove {
+        name: String,
+    },
+}
+
+/// an updates-socket subscriber together with the last message we sent it,
+/// so we only write to it when its rendering actually changes
+struct Subscriber {
+    name: String,
+    stream: UnixStream,
+    last_sent: Option<String>,
 }
 
+const IDLE_JSON: &str =
+    "{\"text\": \"0\", \"alt\": \"standby-focus\", \"tooltip\": \"No timer set\", \"class\": \"idle\"}";
+
 struct ServerState {
-    timer: Timer,
-    subs: Vec<UnixStream>,
+    config: Config,
+    timers: HashMap<String, Timer>,
+    subs: Vec<Subscriber>,
+    /// the notification currently showing for each timer, so a later
+    /// notification for the same timer can close it out instead of leaving
+    /// it (and the thread waiting on its action) orphaned
+    notifications: HashMap<String, notify_rust::NotificationHandle>,
+    /// a handle to the `Arc` this state lives in, so notification action
+    /// listeners can call back into it from another thread
+    self_ref: std::sync::Weak<(Mutex<ServerState>, Condvar)>,
 }
 
 impl ServerState {
-    fn update(&mut self) {
-        // update timer and get waybar string
-        let message = self.timer.update();
+    fn shared(&self) -> SharedState {
+        self.self_ref
+            .upgrade()
+            .expect("ServerState outlived its own Arc")
+    }
+
+    /// the per-timer waybar string, or `None` if `name` isn't known
+    fn render(&self, name: &str) -> Option<String> {
+        if name == AGGREGATE_TIMER_NAME {
+            return Some(self.render_aggregate());
+        }
+        self.timers.get(name).map(|timer| timer.render(&self.config))
+    }
+
+    fn render_aggregate(&self) -> String {
+        let mut summaries: Vec<(&String, String)> = self
+            .timers
+            .iter()
+            .filter_map(|(name, timer)| timer.summary().map(|summary| (name, summary)))
+            .collect();
+        summaries.sort();
+
+        if summaries.is_empty() {
+            return IDLE_JSON.into();
+        }
+
+        let text = summaries
+            .iter()
+            .map(|(name, summary)| format!("{name}: {summary}"))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        format!("{{\"text\": \"{text}\", \"alt\": \"running\", \"tooltip\": \"{text}\", \"class\": \"running\"}}")
+    }
+
+    /// how long until some timer's rendering will next change on its own
+    fn next_wakeup(&self) -> Option<std::time::Duration> {
+        self.timers
+            .values()
+            .filter_map(|timer| timer.next_change(&self.config))
+            .min()
+    }
+
+    /// drops idle timers with no cycle count left to keep, so a timer that was
+    /// canceled while idle (which resets its cycles) doesn't linger in `list`
+    /// (or in memory) forever; an idle timer that finished a cycle is kept so
+    /// its next `start` follows the schedule
+    fn prune_idle(&mut self) {
+        self.timers
+            .retain(|_, timer| !matches!(timer.kind, TimerKind::Idle) || timer.cycles != 0);
+    }
+
+    /// advances every timer and broadcasts to subscribers whose rendering changed
+    fn tick(&mut self) {
+        for timer in self.timers.values_mut() {
+            timer.tick();
+        }
+        self.prune_idle();
 
-        // broadcast it to subscribers
         let mut i: usize = 0;
         loop {
             if i == self.subs.len() {
                 break;
             }
-            match writeln!(self.subs[i], "{}", message) {
+            let message = self
+                .render(&self.subs[i].name)
+                .unwrap_or_else(|| IDLE_JSON.into());
+            if self.subs[i].last_sent.as_deref() == Some(message.as_str()) {
+                i += 1;
+                continue;
+            }
+            match writeln!(self.subs[i].stream, "{}", message) {
                 Ok(()) => {
-                    let _ = self.subs[i].flush();
+                    let _ = self.subs[i].stream.flush();
+                    self.subs[i].last_sent = Some(message);
                     i += 1;
                 }
                 Err(err) => {
@@ -270,41 +583,180 @@ impl ServerState {
                 }
             }
         }
+
+        persist::save(&self.timers);
+    }
+}
+
+impl World for ServerState {
+    fn cancel(&mut self, name: String) -> Result<(), WorldError> {
+        self.timers
+            .get_mut(&name)
+            .ok_or(WorldError::NoTimerExisting)?
+            .cancel(&name)?;
+        self.prune_idle();
+        Ok(())
+    }
+
+    fn start(&mut self, name: String, command: Option<String>) -> Result<(), WorldError> {
+        let config = self.config.clone();
+        let shared = self.shared();
+        self.timers
+            .entry(name.clone())
+            .or_insert_with(Timer::new)
+            .start(&name, &shared, &config, command)
+    }
+
+    fn increase(&mut self, name: String, seconds: i64) -> Result<(), WorldError> {
+        let shared = self.shared();
+        self.timers
+            .get_mut(&name)
+            .ok_or(WorldError::NoTimerExisting)?
+            .increase(&name, &shared, seconds)
+    }
+
+    fn togglepause(&mut self, name: String) -> Result<(), WorldError> {
+        let shared = self.shared();
+        self.timers
+            .get_mut(&name)
+            .ok_or(WorldError::NoTimerExisting)?
+            .togglepause(&name, &shared)
+    }
+
+    fn skip(&mut self, name: String) -> Result<(), WorldError> {
+        let shared = self.shared();
+        self.timers
+            .get_mut(&name)
+            .ok_or(WorldError::NoTimerExisting)?
+            .skip(&name, &shared)
+    }
+
+    fn list(&mut self) -> Result<Vec<String>, WorldError> {
+        let mut names: Vec<String> = self.timers.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn remove(&mut self, name: String) -> Result<(), WorldError> {
+        self.timers
+            .remove(&name)
+            .map(|_| ())
+            .ok_or(WorldError::NoTimerExisting)
     }
 }
 
 fn run_serve() {
-    let state = Arc::new(Mutex::new(ServerState {
-        timer: Timer {
-            cycles: 0,
-            kind: TimerKind::Idle,
-        },
-        subs: Vec::new(),
-    }));
-
-    // spawn a thread which is responsible for calling update in a regular interval
-    let state_thread_interval = state.clone();
-    std::thread::spawn(move || loop {
-        std::thread::sleep(INTERVAL);
-        let mut state = state_thread_interval.lock().unwrap();
-        state.update();
+    // if we were re-exec'd after a SIGHUP, pick up the sockets (and
+    // subscribers) our previous incarnation handed off; otherwise bind fresh ones
+    let (updates_listener, commands_listener, initial_subs) = match restart::inherited() {
+        Some(restart::Inherited {
+            updates_listener,
+            commands_listener,
+            subs,
+        }) => (updates_listener, commands_listener, subs),
+        None => {
+            let updates_listener = UnixListener::bind_addr(
+                &SocketAddr::from_abstract_name(SOCKET_NAME_UPDATES).unwrap(),
+            )
+            .expect("couldn't connect to the \"update\" socket");
+            let commands_listener = UnixListener::bind_addr(
+                &SocketAddr::from_abstract_name(SOCKET_NAME_COMMANDS).unwrap(),
+            )
+            .expect("couldn't connect to the \"commands\" socket");
+            (updates_listener, commands_listener, Vec::new())
+        }
+    };
+
+    let initial_subs = initial_subs
+        .into_iter()
+        .map(|(name, stream)| Subscriber {
+            name,
+            stream,
+            last_sent: None,
+        })
+        .collect();
+
+    let state: SharedState = Arc::new_cyclic(|weak| {
+        (
+            Mutex::new(ServerState {
+                config: Config::load(),
+                timers: persist::load(),
+                subs: initial_subs,
+                notifications: HashMap::new(),
+                self_ref: weak.clone(),
+            }),
+            Condvar::new(),
+        )
+    });
+
+    // installed now that `state` exists, so a SIGHUP can wake its condvar directly
+    restart::install_sighup_handler(state.clone());
+
+    // spawn the scheduler thread: it advances timers exactly when their
+    // rendering is due to change (rather than polling once a second), and
+    // re-execs the daemon once a SIGHUP arrives
+    let state_thread_scheduler = state.clone();
+    let updates_listener_for_restart = updates_listener
+        .try_clone()
+        .expect("couldn't clone the \"update\" socket listener");
+    let commands_listener_for_restart = commands_listener
+        .try_clone()
+        .expect("couldn't clone the \"commands\" socket listener");
+    std::thread::spawn(move || {
+        let (mutex, condvar) = &*state_thread_scheduler;
+        let mut state = mutex.lock().unwrap();
+        loop {
+            if restart::sighup_received() {
+                persist::save(&state.timers);
+                let subs: Vec<(&str, &UnixStream)> = state
+                    .subs
+                    .iter()
+                    .map(|sub| (sub.name.as_str(), &sub.stream))
+                    .collect();
+                let err = restart::reexec_with_handoff(
+                    &updates_listener_for_restart,
+                    &commands_listener_for_restart,
+                    &subs,
+                );
+                eprintln!("couldn't re-exec after SIGHUP: {err}");
+            }
+
+            state.tick();
+            let wait_for = state.next_wakeup().unwrap_or(MAX_IDLE_WAIT);
+            let (guard, _timeout) = condvar.wait_timeout(state, wait_for).unwrap();
+            state = guard;
+        }
     });
 
     // spawn a thread which is responsible for accepting new subscribers
     let state_thread_subaccept = state.clone();
     std::thread::spawn(move || {
-        let listener =
-            UnixListener::bind_addr(&SocketAddr::from_abstract_name(SOCKET_NAME_UPDATES).unwrap())
-                .expect("couldn't connect to the \"update\" socket");
+        let (mutex, condvar) = &*state_thread_subaccept;
+        let listener = updates_listener;
         for stream in listener.incoming() {
             match stream {
-                Ok(stream) => {
-                    // put to list of subscribers and trigger update so that
-                    // the new subscriber gets the current state
-                    let mut state = state_thread_subaccept.lock().unwrap();
+                Ok(mut stream) => {
+                    // the subscriber first tells us which timer it wants rendered
+                    let mut name = String::new();
+                    BufReader::new(&mut stream).read_line(&mut name).unwrap();
+                    let name = if name.trim().is_empty() {
+                        AGGREGATE_TIMER_NAME.to_string()
+                    } else {
+                        name.trim().to_string()
+                    };
                     stream.shutdown(std::net::Shutdown::Read).unwrap();
-                    state.subs.push(stream);
-                    state.update();
+
+                    // put to list of subscribers and wake the scheduler so it
+                    // gets the current state right away
+                    let mut state = mutex.lock().unwrap();
+                    state.subs.push(Subscriber {
+                        name,
+                        stream,
+                        last_sent: None,
+                    });
+                    state.tick();
+                    drop(state);
+                    condvar.notify_one();
                 }
                 Err(err) => {
                     panic!("{err}")
@@ -314,17 +766,18 @@ fn run_serve() {
     });
 
     // the main thread handles requests from the CLI
-    let listener =
-        UnixListener::bind_addr(&SocketAddr::from_abstract_name(SOCKET_NAME_COMMANDS).unwrap())
-            .expect("couldn't connect to the \"commands\" socket");
+    let (mutex, condvar) = &*state;
+    let listener = commands_listener;
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
                 // handles a single remote procedure call
-                let mut state = state.lock().unwrap();
-                state.timer.handle_with(&stream, &stream).unwrap();
+                let mut state = mutex.lock().unwrap();
+                state.handle_with(&stream, &stream).unwrap();
                 stream.shutdown(std::net::Shutdown::Both).unwrap();
-                state.update();
+                state.tick();
+                drop(state);
+                condvar.notify_one();
             }
             Err(err) => {
                 panic!("{err}")
@@ -342,47 +795,63 @@ fn main() -> Result<(), Box<dyn Error>> {
             run_serve();
             Ok(())
         }
-        Args::Hook => {
+        Args::Hook { name } => {
             let mut stream = UnixStream::connect_addr(&socket_addr_updates)?;
+            writeln!(stream, "{name}")?;
             stream.shutdown(std::net::Shutdown::Write)?;
             let mut stdout = std::io::stdout();
             std::io::copy(&mut stream, &mut stdout)?;
             Ok(())
         }
-        Args::New { command } => {
+        Args::New { name, command } => {
             let stream = UnixStream::connect_addr(&socket_addr_commands)?;
-            WorldRPCClient::call_with(&stream, &stream).start(&command)??;
+            WorldRPCClient::call_with(&stream, &stream).start(&name, &command)??;
             stream.shutdown(std::net::Shutdown::Both)?;
             Ok(())
         }
-        Args::Increase { seconds } => {
+        Args::Increase { name, seconds } => {
             let stream = UnixStream::connect_addr(&socket_addr_commands)?;
-            WorldRPCClient::call_with(&stream, &stream).increase(&seconds.into())??;
+            WorldRPCClient::call_with(&stream, &stream).increase(&name, &seconds.into())??;
             stream.shutdown(std::net::Shutdown::Both)?;
             Ok(())
         }
-        Args::Decrease { seconds } => {
+        Args::Decrease { name, seconds } => {
             let seconds: i64 = seconds.into();
             let stream = UnixStream::connect_addr(&socket_addr_commands)?;
-            WorldRPCClient::call_with(&stream, &stream).increase(&-seconds)??;
+            WorldRPCClient::call_with(&stream, &stream).increase(&name, &-seconds)??;
+            stream.shutdown(std::net::Shutdown::Both)?;
+            Ok(())
+        }
+        Args::Togglepause { name } => {
+            let stream = UnixStream::connect_addr(&socket_addr_commands)?;
+            WorldRPCClient::call_with(&stream, &stream).togglepause(&name)??;
             stream.shutdown(std::net::Shutdown::Both)?;
             Ok(())
         }
-        Args::Togglepause => {
+        Args::Skip { name } => {
             let stream = UnixStream::connect_addr(&socket_addr_commands)?;
-            WorldRPCClient::call_with(&stream, &stream).togglepause()??;
+            WorldRPCClient::call_with(&stream, &stream).skip(&name)??;
             stream.shutdown(std::net::Shutdown::Both)?;
             Ok(())
         }
-        Args::Skip => {
+        Args::Cancel { name } => {
             let stream = UnixStream::connect_addr(&socket_addr_commands)?;
-            WorldRPCClient::call_with(&stream, &stream).skip()??;
+            WorldRPCClient::call_with(&stream, &stream).cancel(&name)??;
             stream.shutdown(std::net::Shutdown::Both)?;
             Ok(())
         }
-        Args::Cancel => {
+        Args::List => {
+            let stream = UnixStream::connect_addr(&socket_addr_commands)?;
+            let names = WorldRPCClient::call_with(&stream, &stream).list()??;
+            stream.shutdown(std::net::Shutdown::Both)?;
+            for name in names {
+                println!("{name}");
+            }
+            Ok(())
+        }
+        Args::Remove { name } => {
             let stream = UnixStream::connect_addr(&socket_addr_commands)?;
-            WorldRPCClient::call_with(&stream, &stream).cancel()??;
+            WorldRPCClient::call_with(&stream, &stream).remove(&name)??;
             stream.shutdown(std::net::Shutdown::Both)?;
             Ok(())
         }
@@ -0,0 +1,114 @@
+//! Checkpoints timer state to disk so a `Serve` restart (or crash) doesn't
+//! silently drop whatever is currently running.
+
+use crate::{Timer, TimerKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use time::{Duration, OffsetDateTime};
+
+#[derive(Serialize, Deserialize)]
+struct CheckpointTimer {
+    cycles: i32,
+    kind: CheckpointKind,
+}
+
+#[derive(Serialize, Deserialize)]
+enum CheckpointKind {
+    Idle,
+    Running {
+        /// absolute expiry, as a unix timestamp, so it survives however long the daemon was down
+        expiry_unix: i64,
+        command: Option<String>,
+    },
+    Paused {
+        seconds_left: i64,
+        command: Option<String>,
+    },
+}
+
+impl From<&Timer> for CheckpointTimer {
+    fn from(timer: &Timer) -> Self {
+        let kind = match &timer.kind {
+            TimerKind::Idle => CheckpointKind::Idle,
+            TimerKind::Running { expiry, command } => CheckpointKind::Running {
+                expiry_unix: expiry.unix_timestamp(),
+                command: command.clone(),
+            },
+            TimerKind::Paused { time_left, command } => CheckpointKind::Paused {
+                seconds_left: time_left.whole_seconds(),
+                command: command.clone(),
+            },
+        };
+        CheckpointTimer {
+            cycles: timer.cycles,
+            kind,
+        }
+    }
+}
+
+impl From<CheckpointTimer> for Timer {
+    fn from(checkpoint: CheckpointTimer) -> Self {
+        let kind = match checkpoint.kind {
+            CheckpointKind::Idle => TimerKind::Idle,
+            CheckpointKind::Running {
+                expiry_unix,
+                command,
+            } => TimerKind::Running {
+                expiry: OffsetDateTime::from_unix_timestamp(expiry_unix)
+                    .unwrap_or(OffsetDateTime::UNIX_EPOCH),
+                command,
+            },
+            CheckpointKind::Paused {
+                seconds_left,
+                command,
+            } => TimerKind::Paused {
+                time_left: Duration::seconds(seconds_left),
+                command,
+            },
+        };
+        Timer {
+            cycles: checkpoint.cycles,
+            kind,
+        }
+    }
+}
+
+fn checkpoint_path() -> PathBuf {
+    let dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp".into());
+    PathBuf::from(dir).join("waybar-timer-state.json")
+}
+
+/// Checkpoints the current timers to disk. Best-effort: a failure here
+/// shouldn't take the daemon down.
+pub fn save(timers: &HashMap<String, Timer>) {
+    let checkpoint: HashMap<&String, CheckpointTimer> =
+        timers.iter().map(|(name, timer)| (name, timer.into())).collect();
+    match serde_json::to_string(&checkpoint) {
+        Ok(json) => {
+            if let Err(err) = std::fs::write(checkpoint_path(), json) {
+                eprintln!("couldn't checkpoint timer state: {err}");
+            }
+        }
+        Err(err) => eprintln!("couldn't serialize timer state: {err}"),
+    }
+}
+
+/// Loads whatever timers were checkpointed by a previous run, resuming
+/// `Running` timers based on wall-clock time. Returns an empty map if there's
+/// no checkpoint (e.g. first run).
+pub fn load() -> HashMap<String, Timer> {
+    let Ok(contents) = std::fs::read_to_string(checkpoint_path()) else {
+        return HashMap::new();
+    };
+    match serde_json::from_str::<HashMap<String, CheckpointTimer>>(&contents) {
+        Ok(checkpoint) => checkpoint
+            .into_iter()
+            .map(|(name, timer)| (name, timer.into()))
+            .collect(),
+        Err(err) => {
+            eprintln!("couldn't parse checkpointed timer state: {err}");
+            HashMap::new()
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Lets `Serve` hand its listening sockets (and any already-connected
+//! subscribers) off to a freshly exec'd copy of itself on `SIGHUP`, so
+//! upgrading the binary doesn't drop anyone's connection.
+
+use crate::SharedState;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::process::CommandExt;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// The env var used to pass inherited fds across the re-exec.
+const ENV_INHERITED_FDS: &str = "WAYBAR_TIMER_INHERITED_FDS";
+
+static SIGHUP_RECEIVED: AtomicBool = AtomicBool::new(false);
+/// Write end of the self-pipe the signal handler nudges; -1 until
+/// [`install_sighup_handler`] sets it up.
+static WAKE_WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn handle_sighup(_: libc::c_int) {
+    SIGHUP_RECEIVED.store(true, Ordering::SeqCst);
+    // `Condvar::notify` isn't async-signal-safe, so we can't wake the
+    // scheduler directly here. `write` is, so just nudge the self-pipe; the
+    // thread spawned in `install_sighup_handler` does the actual notifying.
+    let fd = WAKE_WRITE_FD.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let byte = [0u8];
+        unsafe {
+            libc::write(fd, byte.as_ptr() as *const libc::c_void, 1);
+        }
+    }
+}
+
+/// Installs the `SIGHUP` handler and spawns a thread that wakes the
+/// scheduler's condvar as soon as the signal arrives, so an idle daemon
+/// doesn't sit on a `SIGHUP` until `MAX_IDLE_WAIT` elapses. Call once, before
+/// spawning the scheduler thread.
+pub fn install_sighup_handler(shared: SharedState) {
+    let mut fds: [RawFd; 2] = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        panic!(
+            "couldn't create the SIGHUP self-pipe: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    let [read_fd, write_fd] = fds;
+    WAKE_WRITE_FD.store(write_fd, Ordering::SeqCst);
+
+    unsafe {
+        libc::signal(libc::SIGHUP, handle_sighup as libc::sighandler_t);
+    }
+
+    std::thread::spawn(move || {
+        let mut byte = [0u8];
+        loop {
+            let n = unsafe { libc::read(read_fd, byte.as_mut_ptr() as *mut libc::c_void, 1) };
+            if n <= 0 {
+                break;
+            }
+            let (mutex, condvar) = &*shared;
+            let _state = mutex.lock().unwrap();
+            condvar.notify_one();
+        }
+    });
+}
+
+pub fn sighup_received() -> bool {
+    SIGHUP_RECEIVED.swap(false, Ordering::SeqCst)
+}
+
+pub struct Inherited {
+    pub updates_listener: UnixListener,
+    pub commands_listener: UnixListener,
+    pub subs: Vec<(String, UnixStream)>,
+}
+
+/// If we were re-exec'd by [`reexec_with_handoff`], reconstructs the
+/// inherited sockets instead of binding fresh ones.
+///
+/// Entries are `\n`-terminated and subscriber names are length-prefixed
+/// (`sub:<fd>:<name_len>:<name>`) rather than split on a delimiter, since a
+/// subscriber name (a timer name, chosen by whoever ran `waybar-timer hook`)
+/// can itself contain `:` or `,`.
+pub fn inherited() -> Option<Inherited> {
+    let raw = std::env::var(ENV_INHERITED_FDS).ok()?;
+    std::env::remove_var(ENV_INHERITED_FDS);
+
+    let mut updates_listener = None;
+    let mut commands_listener = None;
+    let mut subs = Vec::new();
+
+    let mut cursor = 0;
+    while cursor < raw.len() {
+        let kind_end = find(&raw, cursor, ':');
+        let kind = &raw[cursor..kind_end];
+        cursor = kind_end + 1;
+
+        let fd_end = find(&raw, cursor, if kind == "sub" { ':' } else { '\n' });
+        let fd = parse_fd(&raw[cursor..fd_end]);
+        cursor = fd_end + 1;
+
+        match kind {
+            "updates" => updates_listener = Some(unsafe { UnixListener::from_raw_fd(fd) }),
+            "commands" => commands_listener = Some(unsafe { UnixListener::from_raw_fd(fd) }),
+            "sub" => {
+                let len_end = find(&raw, cursor, ':');
+                let name_len: usize = raw[cursor..len_end]
+                    .parse()
+                    .expect("malformed name length in inherited fd list");
+                cursor = len_end + 1;
+
+                let name = raw[cursor..cursor + name_len].to_string();
+                cursor += name_len;
+                assert_eq!(
+                    raw.as_bytes().get(cursor),
+                    Some(&b'\n'),
+                    "malformed {ENV_INHERITED_FDS} entry: missing name terminator"
+                );
+                cursor += 1;
+
+                subs.push((name, unsafe { UnixStream::from_raw_fd(fd) }));
+            }
+            _ => panic!("malformed {ENV_INHERITED_FDS} entry: unknown kind {kind}"),
+        }
+    }
+
+    Some(Inherited {
+        updates_listener: updates_listener.expect("missing updates listener in handoff"),
+        commands_listener: commands_listener.expect("missing commands listener in handoff"),
+        subs,
+    })
+}
+
+/// byte offset of the next `delim` at or after `from`, panicking if this is a malformed handoff
+fn find(s: &str, from: usize, delim: char) -> usize {
+    s[from..]
+        .find(delim)
+        .map(|offset| from + offset)
+        .unwrap_or_else(|| panic!("malformed {ENV_INHERITED_FDS} entry: missing {delim:?}"))
+}
+
+fn parse_fd(fd: &str) -> RawFd {
+    fd.parse().expect("malformed fd in inherited fd list")
+}
+
+/// Clears `FD_CLOEXEC` on `fd` so it survives the upcoming `execve`.
+fn keep_across_exec(fd: RawFd) {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC);
+    }
+}
+
+/// Re-execs the current binary as `serve`, handing off `updates_listener`,
+/// `commands_listener` and `subs` as inherited file descriptors. Only
+/// returns if the `exec` itself failed.
+pub fn reexec_with_handoff(
+    updates_listener: &UnixListener,
+    commands_listener: &UnixListener,
+    subs: &[(&str, &UnixStream)],
+) -> std::io::Error {
+    keep_across_exec(updates_listener.as_raw_fd());
+    keep_across_exec(commands_listener.as_raw_fd());
+
+    let mut raw = format!("updates:{}\n", updates_listener.as_raw_fd());
+    raw.push_str(&format!("commands:{}\n", commands_listener.as_raw_fd()));
+    for (name, sub) in subs {
+        keep_across_exec(sub.as_raw_fd());
+        raw.push_str(&format!("sub:{}:{}:{name}\n", sub.as_raw_fd(), name.len()));
+    }
+
+    let exe = std::env::current_exe().expect("couldn't resolve current executable");
+    std::process::Command::new(exe)
+        .arg("serve")
+        .env(ENV_INHERITED_FDS, raw)
+        .exec()
+}